@@ -1,4 +1,4 @@
-use caching_scanners::{cli, csv_writer, tree};
+use caching_scanners::{cli, csv_writer, json_writer, tree};
 use clap::{Parser, Subcommand};
 use std::path::{Path, PathBuf};
 use std::process;
@@ -33,6 +33,38 @@ enum Command {
         #[arg(short, long, default_values_t = [String::from("@eaDir")])]
         exclude: Vec<String>,
 
+        /// Glob pattern to exclude from scanning, matched against each
+        /// entry's path relative to `directory` (repeatable)
+        #[arg(short = 'I', long = "ignore")]
+        ignore: Vec<String>,
+
+        /// Keep directories matched by `--ignore` in the state (with no
+        /// files) instead of dropping them entirely
+        #[arg(long)]
+        record_ignored_dirs: bool,
+
+        /// Number of worker threads to scan with
+        #[arg(short, long, default_value_t = 1)]
+        jobs: usize,
+
+        /// Verify unchanged files by BLAKE3 content hash, to catch edits that
+        /// preserve size and mtime
+        #[arg(long)]
+        hash: bool,
+
+        /// Largest file size (in bytes) to verify with `--hash`
+        #[arg(long, default_value_t = caching_scanners::scanner::DEFAULT_HASH_THRESHOLD)]
+        hash_threshold: u64,
+
+        /// Compression codec for the saved state file
+        #[arg(long, default_value = "brotli", value_parser = ["none", "brotli", "zstd"])]
+        compression: String,
+
+        /// Output format: one CSV file, one pretty JSON document, or
+        /// newline-delimited JSON (one object per file entry)
+        #[arg(long, default_value = "csv", value_parser = ["csv", "json", "ndjson"])]
+        format: String,
+
         /// Print cache hit/miss info
         #[arg(short, long)]
         verbose: bool,
@@ -54,14 +86,52 @@ enum Command {
         #[arg(short = 'N', long = "no-escape")]
         no_escape: bool,
 
-        /// Glob pattern to exclude from output (repeatable)
+        /// Glob pattern to exclude from scanning and output (repeatable)
         #[arg(short = 'I', long = "ignore")]
         ignore: Vec<String>,
 
+        /// Keep directories matched by `--ignore` in the state (with no
+        /// files) instead of dropping them entirely
+        #[arg(long)]
+        record_ignored_dirs: bool,
+
         /// Show hidden files (names starting with '.')
         #[arg(short, long)]
         all: bool,
 
+        /// Disk-usage mode: annotate entries with aggregate size and a proportional bar
+        #[arg(long)]
+        du: bool,
+
+        /// Emit a machine-readable JSON tree instead of ASCII art
+        #[arg(long)]
+        json: bool,
+
+        /// Respect each directory's `.gitignore` in addition to `-I` patterns
+        #[arg(long)]
+        gitignore: bool,
+
+        /// Colorize and classify entries using LS_COLORS
+        #[arg(long, default_value = "auto", value_parser = ["auto", "always", "never"])]
+        color: String,
+
+        /// Number of worker threads to scan with
+        #[arg(short, long, default_value_t = 1)]
+        jobs: usize,
+
+        /// Verify unchanged files by BLAKE3 content hash, to catch edits that
+        /// preserve size and mtime
+        #[arg(long)]
+        hash: bool,
+
+        /// Largest file size (in bytes) to verify with `--hash`
+        #[arg(long, default_value_t = caching_scanners::scanner::DEFAULT_HASH_THRESHOLD)]
+        hash_threshold: u64,
+
+        /// Compression codec for the saved state file
+        #[arg(long, default_value = "brotli", value_parser = ["none", "brotli", "zstd"])]
+        compression: String,
+
         /// Print scan info on stderr
         #[arg(short, long)]
         verbose: bool,
@@ -77,18 +147,48 @@ fn main() {
             output,
             state,
             exclude,
+            ignore,
+            record_ignored_dirs,
+            jobs,
+            hash,
+            hash_threshold,
+            compression,
+            format,
+            verbose,
+        } => run_csv(
+            &directory,
+            output,
+            state,
+            &exclude,
+            &ignore,
+            record_ignored_dirs,
+            jobs,
+            hash,
+            hash_threshold,
+            &compression,
+            &format,
             verbose,
-        } => run_csv(&directory, output, state, &exclude, verbose),
+        ),
         Command::Tree {
             directory,
             state,
             exclude,
             no_escape,
             ignore,
+            record_ignored_dirs,
             all,
+            du,
+            json,
+            gitignore,
+            color,
+            jobs,
+            hash,
+            hash_threshold,
+            compression,
             verbose,
         } => run_tree(
-            &directory, state, &exclude, no_escape, &ignore, all, verbose,
+            &directory, state, &exclude, no_escape, &ignore, record_ignored_dirs, all, du, json,
+            gitignore, &color, jobs, hash, hash_threshold, &compression, verbose,
         ),
     }
 }
@@ -98,6 +198,13 @@ fn run_csv(
     output: Option<PathBuf>,
     state: Option<PathBuf>,
     exclude: &[String],
+    ignore: &[String],
+    record_ignored_dirs: bool,
+    jobs: usize,
+    hash: bool,
+    hash_threshold: u64,
+    compression: &str,
+    format: &str,
     verbose: bool,
 ) {
     if !root.is_dir() {
@@ -105,21 +212,38 @@ fn run_csv(
         process::exit(1);
     }
 
-    let output = output.unwrap_or_else(|| root.join("index.csv"));
+    let output = output.unwrap_or_else(|| root.join(format!("index.{format}")));
     let state_path = state.unwrap_or_else(|| root.join(".fsscan.state"));
+    let patterns = cli::parse_ignore_patterns(ignore);
 
     let mut scan_state = cli::load_state(&state_path, verbose);
-    cli::run_scan(root, &mut scan_state, exclude, verbose);
+    cli::run_scan_with_jobs(
+        root,
+        &mut scan_state,
+        exclude,
+        verbose,
+        jobs,
+        hash,
+        hash_threshold,
+        &patterns,
+        record_ignored_dirs,
+    );
 
-    if let Err(e) = csv_writer::write_csv(&scan_state, &output) {
-        eprintln!("error writing CSV: {}", e);
+    let write_result = match format {
+        "json" => json_writer::write_json(&scan_state, &output),
+        "ndjson" => json_writer::write_ndjson(&scan_state, &output),
+        _ => csv_writer::write_csv(&scan_state, &output),
+    };
+    if let Err(e) = write_result {
+        eprintln!("error writing {}: {}", format, e);
         process::exit(1);
     }
     if verbose {
         eprintln!("wrote {}", output.display());
     }
 
-    cli::save_state(&scan_state, &state_path, verbose);
+    let codec = caching_scanners::state::parse_codec(compression);
+    cli::save_state(&scan_state, &state_path, codec, verbose);
 }
 
 fn run_tree(
@@ -128,7 +252,16 @@ fn run_tree(
     exclude: &[String],
     no_escape: bool,
     ignore: &[String],
+    record_ignored_dirs: bool,
     all: bool,
+    du: bool,
+    json: bool,
+    gitignore: bool,
+    color: &str,
+    jobs: usize,
+    hash: bool,
+    hash_threshold: u64,
+    compression: &str,
     verbose: bool,
 ) {
     if verbose {
@@ -143,13 +276,36 @@ fn run_tree(
     if verbose {
         eprintln!("state_path is {}", state_path.display());
     }
+    let patterns = cli::parse_ignore_patterns(ignore);
 
     let mut scan_state = cli::load_state(&state_path, verbose);
-    cli::run_scan(root, &mut scan_state, exclude, verbose);
-    cli::save_state(&scan_state, &state_path, verbose);
+    cli::run_scan_with_jobs(
+        root,
+        &mut scan_state,
+        exclude,
+        verbose,
+        jobs,
+        hash,
+        hash_threshold,
+        &patterns,
+        record_ignored_dirs,
+    );
+    let codec = caching_scanners::state::parse_codec(compression);
+    cli::save_state(&scan_state, &state_path, codec, verbose);
 
-    let patterns = cli::parse_ignore_patterns(ignore);
+    if json {
+        if let Err(e) = tree::render_tree_json(&scan_state, root, &patterns, all, gitignore) {
+            eprintln!("error writing JSON tree: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
 
-    let (dir_count, file_count) = tree::render_tree(&scan_state, root, &patterns, no_escape, all);
+    let use_color = tree::resolve_color(color);
+    let (dir_count, file_count) = if du {
+        tree::render_tree_du(&scan_state, root, &patterns, no_escape, all, gitignore, use_color)
+    } else {
+        tree::render_tree(&scan_state, root, &patterns, no_escape, all, gitignore, use_color)
+    };
     println!("\n{} directories, {} files", dir_count, file_count);
 }
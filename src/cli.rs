@@ -1,5 +1,5 @@
 use crate::scanner;
-use crate::state::{LoadOutcome, ScanState};
+use crate::state::{Codec, LoadOutcome, ScanState};
 use std::path::Path;
 use std::process;
 
@@ -28,7 +28,73 @@ pub fn load_state(path: &Path, verbose: bool) -> ScanState {
 
 /// Run the scanner and log stats. Exits on error.
 pub fn run_scan(root: &Path, state: &mut ScanState, exclude: &[String], verbose: bool) {
-    match scanner::scan(root, state, exclude, verbose) {
+    run_scan_with_jobs(
+        root,
+        state,
+        exclude,
+        verbose,
+        1,
+        false,
+        scanner::DEFAULT_HASH_THRESHOLD,
+        &[],
+        false,
+    )
+}
+
+/// Run the scanner across `jobs` worker threads, printing live progress on
+/// `--verbose`. Exits on error. When `hash` is set, files whose size/mtime look
+/// unchanged are additionally verified by BLAKE3 content hash. `ignore_patterns`
+/// (see `parse_ignore_patterns`) prunes matching files and directories from the
+/// walk itself; `record_ignored_dirs` keeps ignored directories in the saved
+/// state (with no files) instead of dropping them entirely. See
+/// `scanner::scan_with_jobs`.
+pub fn run_scan_with_jobs(
+    root: &Path,
+    state: &mut ScanState,
+    exclude: &[String],
+    verbose: bool,
+    jobs: usize,
+    hash: bool,
+    hash_threshold: u64,
+    ignore_patterns: &[glob::Pattern],
+    record_ignored_dirs: bool,
+) {
+    let (tx, rx) = if verbose {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        (Some(tx), Some(rx))
+    } else {
+        (None, None)
+    };
+
+    let printer = rx.map(|rx: crossbeam_channel::Receiver<scanner::ProgressData>| {
+        std::thread::spawn(move || {
+            for update in rx {
+                eprintln!(
+                    "{}: {}/{} dirs",
+                    update.current_stage, update.dirs_scanned, update.dirs_to_check
+                );
+            }
+        })
+    });
+
+    let result = scanner::scan_with_jobs(
+        root,
+        state,
+        exclude,
+        verbose,
+        jobs,
+        tx,
+        hash,
+        hash_threshold,
+        ignore_patterns,
+        record_ignored_dirs,
+    );
+
+    if let Some(printer) = printer {
+        let _ = printer.join();
+    }
+
+    match result {
         Ok(stats) => {
             if verbose {
                 eprintln!(
@@ -58,9 +124,9 @@ pub fn parse_ignore_patterns(patterns: &[String]) -> Vec<glob::Pattern> {
         .collect()
 }
 
-/// Save scan state to disk. Exits on error.
-pub fn save_state(state: &ScanState, path: &Path, verbose: bool) {
-    if let Err(e) = state.save(path) {
+/// Save scan state to disk, compressed with `codec`. Exits on error.
+pub fn save_state(state: &ScanState, path: &Path, codec: Codec, verbose: bool) {
+    if let Err(e) = state.save_with_codec(path, codec) {
         eprintln!("error saving state: {}", e);
         process::exit(1);
     }
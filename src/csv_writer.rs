@@ -1,13 +1,51 @@
-use caching_scanners::state::ScanState;
+use caching_scanners::state::{FileKind, ScanState};
 use std::io;
 use std::path::Path;
 
+/// Per-kind detail for the CSV `detail` column: a symlink's target, or a
+/// device node's `rdev`. Empty for kinds that carry neither.
+fn kind_detail(kind: &FileKind) -> String {
+    match kind {
+        FileKind::Symlink { target } => target.clone(),
+        FileKind::BlockDevice { rdev } | FileKind::CharDevice { rdev } => rdev.to_string(),
+        FileKind::Regular
+        | FileKind::Fifo
+        | FileKind::Socket
+        | FileKind::Directory
+        | FileKind::Unknown => String::new(),
+    }
+}
+
+/// Hex-encode a `--hash`-captured content hash for the CSV `hash` column.
+/// Empty when hashing was disabled, skipped, or inconclusive for this entry.
+fn hash_column(hash: Option<[u8; 32]>) -> String {
+    match hash {
+        Some(digest) => digest.iter().map(|b| format!("{b:02x}")).collect(),
+        None => String::new(),
+    }
+}
+
+/// Encode a file's xattrs for the CSV `xattrs` column as `name=hex(value)`
+/// pairs joined by `;`. Empty when the file has none.
+fn xattrs_column(xattrs: &[(String, Vec<u8>)]) -> String {
+    xattrs
+        .iter()
+        .map(|(name, value)| {
+            let hex: String = value.iter().map(|b| format!("{b:02x}")).collect();
+            format!("{name}={hex}")
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
 pub fn write_csv(state: &ScanState, output: &Path) -> io::Result<()> {
     let file = std::fs::File::create(output)?;
     let mut wtr = csv::Writer::from_writer(file);
 
-    wtr.write_record(["path", "size", "ctime", "mtime"])
-        .map_err(io::Error::other)?;
+    wtr.write_record([
+        "path", "size", "ctime", "mtime", "kind", "detail", "hash", "xattrs",
+    ])
+    .map_err(io::Error::other)?;
 
     // Sort directories for stable output
     let mut dirs: Vec<_> = state.dirs.keys().collect();
@@ -16,12 +54,16 @@ pub fn write_csv(state: &ScanState, output: &Path) -> io::Result<()> {
     for dir in dirs {
         let entry = &state.dirs[dir];
         for file in &entry.files {
-            let path = dir.join(&file.filename);
+            let path = Path::new(dir).join(&file.filename);
             wtr.write_record([
                 path.to_string_lossy().as_ref(),
                 &file.size.to_string(),
                 &file.ctime.to_string(),
                 &file.mtime.to_string(),
+                file.kind.label(),
+                &kind_detail(&file.kind),
+                &hash_column(file.content_hash),
+                &xattrs_column(&file.xattrs),
             ])
             .map_err(io::Error::other)?;
         }
@@ -34,8 +76,7 @@ pub fn write_csv(state: &ScanState, output: &Path) -> io::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use caching_scanners::state::{DirEntry, FileEntry};
-    use std::path::PathBuf;
+    use caching_scanners::state::{DirEntry, FileEntry, FileKind};
 
     fn read_csv(path: &Path) -> String {
         std::fs::read_to_string(path).unwrap()
@@ -50,7 +91,7 @@ mod tests {
         write_csv(&state, &csv_path).unwrap();
 
         let content = read_csv(&csv_path);
-        assert_eq!(content, "path,size,ctime,mtime\n");
+        assert_eq!(content, "path,size,ctime,mtime,kind,detail,hash,xattrs\n");
     }
 
     #[test]
@@ -60,15 +101,23 @@ mod tests {
 
         let mut state = ScanState::default();
         state.dirs.insert(
-            PathBuf::from("/data"),
+            "/data".into(),
             DirEntry {
                 dir_mtime: 100,
+                dir_mtime_nsec: 0,
                 files: vec![FileEntry {
                     filename: "file.txt".into(),
                     size: 42,
                     ctime: 1000,
                     mtime: 2000,
+                    mtime_nsec: 0,
+                    mode: 0o100644,
+                    mtime_ambiguous: false,
+                    kind: FileKind::Regular,
+                    xattrs: Vec::new(),
+                    content_hash: None,
                 }],
+                mtime_ambiguous: false,
             },
         );
         write_csv(&state, &csv_path).unwrap();
@@ -76,8 +125,115 @@ mod tests {
         let content = read_csv(&csv_path);
         let lines: Vec<&str> = content.lines().collect();
         assert_eq!(lines.len(), 2);
-        assert_eq!(lines[0], "path,size,ctime,mtime");
-        assert_eq!(lines[1], "/data/file.txt,42,1000,2000");
+        assert_eq!(lines[0], "path,size,ctime,mtime,kind,detail,hash,xattrs");
+        assert_eq!(lines[1], "/data/file.txt,42,1000,2000,file,,,");
+    }
+
+    #[test]
+    fn symlink_kind_and_target_are_surfaced() {
+        let tmp = tempfile::tempdir().unwrap();
+        let csv_path = tmp.path().join("out.csv");
+
+        let mut state = ScanState::default();
+        state.dirs.insert(
+            "/data".into(),
+            DirEntry {
+                dir_mtime: 100,
+                dir_mtime_nsec: 0,
+                files: vec![FileEntry {
+                    filename: "link".into(),
+                    size: 0,
+                    ctime: 0,
+                    mtime: 0,
+                    mtime_nsec: 0,
+                    mode: 0o120777,
+                    mtime_ambiguous: false,
+                    kind: FileKind::Symlink {
+                        target: "target.txt".into(),
+                    },
+                    xattrs: Vec::new(),
+                    content_hash: None,
+                }],
+                mtime_ambiguous: false,
+            },
+        );
+        write_csv(&state, &csv_path).unwrap();
+
+        let content = read_csv(&csv_path);
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[1], "/data/link,0,0,0,symlink,target.txt,,");
+    }
+
+    #[test]
+    fn content_hash_is_hex_encoded() {
+        let tmp = tempfile::tempdir().unwrap();
+        let csv_path = tmp.path().join("out.csv");
+
+        let mut state = ScanState::default();
+        state.dirs.insert(
+            "/data".into(),
+            DirEntry {
+                dir_mtime: 100,
+                dir_mtime_nsec: 0,
+                files: vec![FileEntry {
+                    filename: "file.txt".into(),
+                    size: 42,
+                    ctime: 1000,
+                    mtime: 2000,
+                    mtime_nsec: 0,
+                    mode: 0o100644,
+                    mtime_ambiguous: false,
+                    kind: FileKind::Regular,
+                    xattrs: Vec::new(),
+                    content_hash: Some([0xab; 32]),
+                }],
+                mtime_ambiguous: false,
+            },
+        );
+        write_csv(&state, &csv_path).unwrap();
+
+        let content = read_csv(&csv_path);
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(
+            lines[1],
+            format!("/data/file.txt,42,1000,2000,file,,{},", "ab".repeat(32))
+        );
+    }
+
+    #[test]
+    fn xattrs_are_encoded_as_name_hex_pairs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let csv_path = tmp.path().join("out.csv");
+
+        let mut state = ScanState::default();
+        state.dirs.insert(
+            "/data".into(),
+            DirEntry {
+                dir_mtime: 100,
+                dir_mtime_nsec: 0,
+                files: vec![FileEntry {
+                    filename: "file.txt".into(),
+                    size: 1,
+                    ctime: 0,
+                    mtime: 0,
+                    mtime_nsec: 0,
+                    mode: 0o100644,
+                    mtime_ambiguous: false,
+                    kind: FileKind::Regular,
+                    xattrs: vec![
+                        ("user.a".into(), vec![0xde, 0xad]),
+                        ("user.b".into(), vec![0xbe, 0xef]),
+                    ],
+                    content_hash: None,
+                }],
+                mtime_ambiguous: false,
+            },
+        );
+        write_csv(&state, &csv_path).unwrap();
+
+        let content = read_csv(&csv_path);
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[1], "/data/file.txt,1,0,0,file,,,user.a=dead;user.b=beef");
     }
 
     #[test]
@@ -89,15 +245,23 @@ mod tests {
         // Insert in reverse order
         for name in &["/z_dir", "/a_dir", "/m_dir"] {
             state.dirs.insert(
-                PathBuf::from(name),
+                (*name).into(),
                 DirEntry {
                     dir_mtime: 100,
+                    dir_mtime_nsec: 0,
                     files: vec![FileEntry {
                         filename: "f.txt".into(),
                         size: 1,
                         ctime: 0,
                         mtime: 0,
+                        mtime_nsec: 0,
+                        mode: 0o100644,
+                        mtime_ambiguous: false,
+                        kind: FileKind::Regular,
+                        xattrs: Vec::new(),
+                        content_hash: None,
                     }],
+                    mtime_ambiguous: false,
                 },
             );
         }
@@ -119,14 +283,16 @@ mod tests {
 
         let mut state = ScanState::default();
         state.dirs.insert(
-            PathBuf::from("/dir"),
+            "/dir".into(),
             DirEntry {
                 dir_mtime: 100,
+                dir_mtime_nsec: 0,
                 files: vec![
-                    FileEntry { filename: "second.txt".into(), size: 1, ctime: 0, mtime: 0 },
-                    FileEntry { filename: "first.txt".into(), size: 2, ctime: 0, mtime: 0 },
-                    FileEntry { filename: "third.txt".into(), size: 3, ctime: 0, mtime: 0 },
+                    FileEntry { filename: "second.txt".into(), size: 1, ctime: 0, mtime: 0, mtime_nsec: 0, mode: 0o100644, mtime_ambiguous: false, kind: FileKind::Regular, xattrs: Vec::new(), content_hash: None },
+                    FileEntry { filename: "first.txt".into(), size: 2, ctime: 0, mtime: 0, mtime_nsec: 0, mode: 0o100644, mtime_ambiguous: false, kind: FileKind::Regular, xattrs: Vec::new(), content_hash: None },
+                    FileEntry { filename: "third.txt".into(), size: 3, ctime: 0, mtime: 0, mtime_nsec: 0, mode: 0o100644, mtime_ambiguous: false, kind: FileKind::Regular, xattrs: Vec::new(), content_hash: None },
                 ],
+                mtime_ambiguous: false,
             },
         );
         write_csv(&state, &csv_path).unwrap();
@@ -0,0 +1,214 @@
+//! Minimal `.gitignore`-style pattern matching for the `tree --gitignore` flag.
+//!
+//! This is not a full implementation of git's ignore rules (no `.git/info/exclude`,
+//! no global excludesfile, no escaped special characters) but covers the common
+//! cases: `/`-anchoring, trailing-`/` directory-only rules, `**` recursive globs,
+//! and `!`-negation.
+
+use glob::Pattern;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A single parsed `.gitignore` line.
+struct IgnoreRule {
+    pattern: Pattern,
+    dir_only: bool,
+    negated: bool,
+}
+
+impl IgnoreRule {
+    /// Parse one line of a `.gitignore` file; returns `None` for blank lines and comments.
+    fn parse(line: &str) -> Option<IgnoreRule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let negated = line.starts_with('!');
+        let body = if negated { &line[1..] } else { line };
+
+        let dir_only = body.ends_with('/');
+        let body = body.strip_suffix('/').unwrap_or(body);
+
+        // A pattern containing a `/` anywhere but the last character is anchored
+        // to the directory holding the `.gitignore`; one with no inner `/` matches
+        // at any depth beneath it.
+        let anchored = body.starts_with('/') || body[..body.len().saturating_sub(1)].contains('/');
+        let body = body.strip_prefix('/').unwrap_or(body);
+
+        let glob_str = if anchored {
+            body.to_string()
+        } else {
+            format!("**/{body}")
+        };
+
+        Pattern::new(&glob_str).ok().map(|pattern| IgnoreRule {
+            pattern,
+            dir_only,
+            negated,
+        })
+    }
+
+    fn matches(&self, rel_path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        self.pattern.matches(rel_path)
+    }
+}
+
+/// A stack of `.gitignore` rule sets, one per directory level already descended
+/// into. Deeper levels and later `!`-negations take precedence, matching git's
+/// own resolution order.
+///
+/// A level is only added by an explicit `push` call as the caller walks into a
+/// directory (see `tree::render_dir`), and the caller never walks into a
+/// directory that `tree::merge_entries` has excluded. So a `!`-negation
+/// inside an ignored directory's `.gitignore` is never even loaded onto the
+/// stack and can't re-include anything beneath it — this is intentional and
+/// matches git's own documented limitation: once a directory is excluded, git
+/// does not scan it for negated patterns either.
+#[derive(Default, Clone)]
+pub struct IgnoreStack {
+    levels: Vec<(PathBuf, Vec<IgnoreRule>)>,
+}
+
+impl Clone for IgnoreRule {
+    fn clone(&self) -> Self {
+        IgnoreRule {
+            pattern: self.pattern.clone(),
+            dir_only: self.dir_only,
+            negated: self.negated,
+        }
+    }
+}
+
+impl IgnoreStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return a new stack with `dir`'s `.gitignore` (if any) layered on top.
+    pub fn push(&self, dir: &Path) -> IgnoreStack {
+        let mut levels = self.levels.clone();
+        if let Ok(contents) = fs::read_to_string(dir.join(".gitignore")) {
+            let rules: Vec<IgnoreRule> = contents.lines().filter_map(IgnoreRule::parse).collect();
+            if !rules.is_empty() {
+                levels.push((dir.to_path_buf(), rules));
+            }
+        }
+        IgnoreStack { levels }
+    }
+
+    /// Whether `path` (a direct or nested entry under one of this stack's
+    /// directories) is ignored, applying each level's rules in order so a
+    /// deeper or later `!`-negation can re-include something an earlier rule
+    /// excluded.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for (base, rules) in &self.levels {
+            let Ok(rel) = path.strip_prefix(base) else {
+                continue;
+            };
+            let rel_str = rel.to_string_lossy().replace('\\', "/");
+            for rule in rules {
+                if rule.matches(&rel_str, is_dir) {
+                    ignored = !rule.negated;
+                }
+            }
+        }
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ignores_blank_lines_and_comments() {
+        assert!(IgnoreRule::parse("").is_none());
+        assert!(IgnoreRule::parse("   ").is_none());
+        assert!(IgnoreRule::parse("# a comment").is_none());
+    }
+
+    #[test]
+    fn parse_recognizes_negation_and_dir_only() {
+        let rule = IgnoreRule::parse("!keep.txt").unwrap();
+        assert!(rule.negated);
+        assert!(!rule.dir_only);
+
+        let rule = IgnoreRule::parse("build/").unwrap();
+        assert!(!rule.negated);
+        assert!(rule.dir_only);
+
+        let rule = IgnoreRule::parse("!cache/").unwrap();
+        assert!(rule.negated);
+        assert!(rule.dir_only);
+    }
+
+    #[test]
+    fn unanchored_pattern_matches_at_any_depth() {
+        let rule = IgnoreRule::parse("*.log").unwrap();
+        assert!(rule.matches("a.log", false));
+        assert!(rule.matches("nested/b.log", false));
+    }
+
+    #[test]
+    fn anchored_pattern_only_matches_at_its_own_level() {
+        let rule = IgnoreRule::parse("/build").unwrap();
+        assert!(rule.matches("build", true));
+        assert!(!rule.matches("nested/build", true));
+
+        let rule = IgnoreRule::parse("src/build").unwrap();
+        assert!(rule.matches("src/build", true));
+        assert!(!rule.matches("nested/src/build", true));
+    }
+
+    #[test]
+    fn dir_only_rule_does_not_match_a_plain_file() {
+        let rule = IgnoreRule::parse("build/").unwrap();
+        assert!(rule.matches("build", true));
+        assert!(!rule.matches("build", false));
+    }
+
+    #[test]
+    fn is_ignored_applies_rules_from_the_matching_level_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join(".gitignore"), "*.log\n").unwrap();
+        let stack = IgnoreStack::new().push(tmp.path());
+
+        assert!(stack.is_ignored(&tmp.path().join("a.log"), false));
+        assert!(!stack.is_ignored(&tmp.path().join("a.txt"), false));
+    }
+
+    #[test]
+    fn later_negation_re_includes_an_earlier_excluded_entry() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join(".gitignore"), "*.log\n!keep.log\n").unwrap();
+        let stack = IgnoreStack::new().push(tmp.path());
+
+        assert!(stack.is_ignored(&tmp.path().join("other.log"), false));
+        assert!(!stack.is_ignored(&tmp.path().join("keep.log"), false));
+    }
+
+    #[test]
+    fn a_deeper_levels_rule_overrides_a_shallower_ones() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join(".gitignore"), "build\n").unwrap();
+        let sub = tmp.path().join("sub");
+        std::fs::create_dir(&sub).unwrap();
+        std::fs::write(sub.join(".gitignore"), "!build\n").unwrap();
+
+        let stack = IgnoreStack::new().push(tmp.path()).push(&sub);
+
+        assert!(stack.is_ignored(&tmp.path().join("build"), true));
+        assert!(!stack.is_ignored(&sub.join("build"), true));
+    }
+
+    #[test]
+    fn push_is_a_no_op_for_a_directory_with_no_gitignore() {
+        let tmp = tempfile::tempdir().unwrap();
+        let stack = IgnoreStack::new().push(tmp.path());
+        assert!(!stack.is_ignored(&tmp.path().join("anything"), false));
+    }
+}
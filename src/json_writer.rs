@@ -0,0 +1,351 @@
+use caching_scanners::state::{FileEntry, FileKind, ScanState};
+use serde_json::json;
+use std::io;
+use std::path::Path;
+
+/// Per-kind detail for the `detail` field: a symlink's target, or a device
+/// node's `rdev`. Omitted for kinds that carry neither.
+fn kind_detail(kind: &FileKind) -> Option<serde_json::Value> {
+    match kind {
+        FileKind::Symlink { target } => Some(json!(target)),
+        FileKind::BlockDevice { rdev } | FileKind::CharDevice { rdev } => Some(json!(rdev)),
+        FileKind::Regular
+        | FileKind::Fifo
+        | FileKind::Socket
+        | FileKind::Directory
+        | FileKind::Unknown => None,
+    }
+}
+
+/// Hex-encode a `--hash`-captured content hash for the `hash` field. Omitted
+/// when hashing was disabled, skipped, or inconclusive for this entry.
+fn hash_field(hash: Option<[u8; 32]>) -> Option<serde_json::Value> {
+    hash.map(|digest| json!(digest.iter().map(|b| format!("{b:02x}")).collect::<String>()))
+}
+
+/// A file's xattrs for the `xattrs` field: an array of `{name, value}`
+/// objects, value hex-encoded. Empty array when the file has none.
+fn xattrs_field(xattrs: &[(String, Vec<u8>)]) -> serde_json::Value {
+    json!(
+        xattrs
+            .iter()
+            .map(|(name, value)| json!({
+                "name": name,
+                "value": value.iter().map(|b| format!("{b:02x}")).collect::<String>(),
+            }))
+            .collect::<Vec<_>>()
+    )
+}
+
+/// Build the JSON object for a single file entry, shared by `write_json` and
+/// `write_ndjson` so the two formats describe a file the same way.
+fn file_object(path: &Path, file: &FileEntry) -> serde_json::Value {
+    let mut obj = json!({
+        "path": path.to_string_lossy(),
+        "filename": file.filename,
+        "size": file.size,
+        "ctime": file.ctime,
+        "mtime": file.mtime,
+        "mtime_nsec": file.mtime_nsec,
+        "kind": file.kind.label(),
+        "xattrs": xattrs_field(&file.xattrs),
+    });
+    if let Some(detail) = kind_detail(&file.kind) {
+        obj["detail"] = detail;
+    }
+    if let Some(hash) = hash_field(file.content_hash) {
+        obj["hash"] = hash;
+    }
+    obj
+}
+
+/// Write one pretty-printed JSON document describing the whole `ScanState`
+/// tree: a sorted array of directories, each with its files nested inside.
+/// For a flat, streamable alternative see `write_ndjson`.
+pub fn write_json(state: &ScanState, output: &Path) -> io::Result<()> {
+    let mut dirs: Vec<_> = state.dirs.keys().collect();
+    dirs.sort();
+
+    let tree: Vec<serde_json::Value> = dirs
+        .into_iter()
+        .map(|dir| {
+            let entry = &state.dirs[dir];
+            let files: Vec<serde_json::Value> = entry
+                .files
+                .iter()
+                .map(|file| file_object(&Path::new(dir).join(&file.filename), file))
+                .collect();
+            json!({
+                "path": dir,
+                "dir_mtime": entry.dir_mtime,
+                "dir_mtime_nsec": entry.dir_mtime_nsec,
+                "files": files,
+            })
+        })
+        .collect();
+
+    let document =
+        serde_json::to_string_pretty(&json!({ "directories": tree })).map_err(io::Error::other)?;
+    std::fs::write(output, document)
+}
+
+/// Write newline-delimited JSON, one object per file entry, so callers can
+/// pipe enormous scans into tools like `jq` without loading the whole result
+/// into memory. Directories are sorted for stable output, same as `write_json`
+/// and `csv_writer::write_csv`.
+pub fn write_ndjson(state: &ScanState, output: &Path) -> io::Result<()> {
+    let mut dirs: Vec<_> = state.dirs.keys().collect();
+    dirs.sort();
+
+    let mut out = String::new();
+    for dir in dirs {
+        let entry = &state.dirs[dir];
+        for file in &entry.files {
+            let obj = file_object(&Path::new(dir).join(&file.filename), file);
+            out.push_str(&obj.to_string());
+            out.push('\n');
+        }
+    }
+
+    std::fs::write(output, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use caching_scanners::state::DirEntry;
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn regular_entry(filename: &str, size: u64, content_hash: Option<[u8; 32]>) -> FileEntry {
+        FileEntry {
+            filename: filename.into(),
+            size,
+            ctime: 1000,
+            mtime: 2000,
+            mtime_nsec: 0,
+            mode: 0o100644,
+            mtime_ambiguous: false,
+            kind: FileKind::Regular,
+            xattrs: Vec::new(),
+            content_hash,
+        }
+    }
+
+    #[test]
+    fn empty_state_produces_empty_directories_array() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("out.json");
+
+        let state = ScanState::default();
+        write_json(&state, &out).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&read(&out)).unwrap();
+        assert_eq!(value["directories"], json!([]));
+    }
+
+    #[test]
+    fn write_json_nests_files_under_their_directory() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("out.json");
+
+        let mut state = ScanState::default();
+        state.dirs.insert(
+            "/data".into(),
+            DirEntry {
+                dir_mtime: 100,
+                dir_mtime_nsec: 0,
+                files: vec![regular_entry("file.txt", 42, None)],
+                mtime_ambiguous: false,
+            },
+        );
+        write_json(&state, &out).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&read(&out)).unwrap();
+        let dir = &value["directories"][0];
+        assert_eq!(dir["path"], "/data");
+        assert_eq!(dir["files"][0]["path"], "/data/file.txt");
+        assert_eq!(dir["files"][0]["size"], 42);
+        assert_eq!(dir["files"][0]["kind"], "file");
+        assert!(dir["files"][0].get("hash").is_none());
+    }
+
+    #[test]
+    fn symlink_target_is_surfaced_as_detail() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("out.json");
+
+        let mut state = ScanState::default();
+        state.dirs.insert(
+            "/data".into(),
+            DirEntry {
+                dir_mtime: 100,
+                dir_mtime_nsec: 0,
+                files: vec![FileEntry {
+                    filename: "link".into(),
+                    size: 0,
+                    ctime: 0,
+                    mtime: 0,
+                    mtime_nsec: 0,
+                    mode: 0o120777,
+                    mtime_ambiguous: false,
+                    kind: FileKind::Symlink {
+                        target: "target.txt".into(),
+                    },
+                    xattrs: Vec::new(),
+                    content_hash: None,
+                }],
+                mtime_ambiguous: false,
+            },
+        );
+        write_json(&state, &out).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&read(&out)).unwrap();
+        assert_eq!(value["directories"][0]["files"][0]["detail"], "target.txt");
+    }
+
+    #[test]
+    fn content_hash_is_hex_encoded() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("out.json");
+
+        let mut state = ScanState::default();
+        state.dirs.insert(
+            "/data".into(),
+            DirEntry {
+                dir_mtime: 100,
+                dir_mtime_nsec: 0,
+                files: vec![regular_entry("file.txt", 42, Some([0xab; 32]))],
+                mtime_ambiguous: false,
+            },
+        );
+        write_json(&state, &out).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&read(&out)).unwrap();
+        assert_eq!(
+            value["directories"][0]["files"][0]["hash"],
+            "ab".repeat(32)
+        );
+    }
+
+    #[test]
+    fn xattrs_are_surfaced_as_name_value_objects() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("out.json");
+
+        let mut state = ScanState::default();
+        state.dirs.insert(
+            "/data".into(),
+            DirEntry {
+                dir_mtime: 100,
+                dir_mtime_nsec: 0,
+                files: vec![FileEntry {
+                    filename: "file.txt".into(),
+                    size: 1,
+                    ctime: 0,
+                    mtime: 0,
+                    mtime_nsec: 0,
+                    mode: 0o100644,
+                    mtime_ambiguous: false,
+                    kind: FileKind::Regular,
+                    xattrs: vec![("user.comment".into(), vec![0xde, 0xad])],
+                    content_hash: None,
+                }],
+                mtime_ambiguous: false,
+            },
+        );
+        write_json(&state, &out).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&read(&out)).unwrap();
+        let xattrs = &value["directories"][0]["files"][0]["xattrs"];
+        assert_eq!(xattrs[0]["name"], "user.comment");
+        assert_eq!(xattrs[0]["value"], "dead");
+    }
+
+    #[test]
+    fn a_file_with_no_xattrs_gets_an_empty_xattrs_array() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("out.json");
+
+        let mut state = ScanState::default();
+        state.dirs.insert(
+            "/data".into(),
+            DirEntry {
+                dir_mtime: 100,
+                dir_mtime_nsec: 0,
+                files: vec![regular_entry("file.txt", 1, None)],
+                mtime_ambiguous: false,
+            },
+        );
+        write_json(&state, &out).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&read(&out)).unwrap();
+        assert_eq!(value["directories"][0]["files"][0]["xattrs"], json!([]));
+    }
+
+    #[test]
+    fn ndjson_also_carries_xattrs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("out.ndjson");
+
+        let mut state = ScanState::default();
+        state.dirs.insert(
+            "/data".into(),
+            DirEntry {
+                dir_mtime: 100,
+                dir_mtime_nsec: 0,
+                files: vec![FileEntry {
+                    filename: "file.txt".into(),
+                    size: 1,
+                    ctime: 0,
+                    mtime: 0,
+                    mtime_nsec: 0,
+                    mode: 0o100644,
+                    mtime_ambiguous: false,
+                    kind: FileKind::Regular,
+                    xattrs: vec![("user.comment".into(), vec![0xbe, 0xef])],
+                    content_hash: None,
+                }],
+                mtime_ambiguous: false,
+            },
+        );
+        write_ndjson(&state, &out).unwrap();
+
+        let content = read(&out);
+        let value: serde_json::Value = serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        assert_eq!(value["xattrs"][0]["name"], "user.comment");
+        assert_eq!(value["xattrs"][0]["value"], "beef");
+    }
+
+    #[test]
+    fn ndjson_writes_one_line_per_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("out.ndjson");
+
+        let mut state = ScanState::default();
+        state.dirs.insert(
+            "/data".into(),
+            DirEntry {
+                dir_mtime: 100,
+                dir_mtime_nsec: 0,
+                files: vec![
+                    regular_entry("a.txt", 1, None),
+                    regular_entry("b.txt", 2, None),
+                ],
+                mtime_ambiguous: false,
+            },
+        );
+        write_ndjson(&state, &out).unwrap();
+
+        let content = read(&out);
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["path"], "/data/a.txt");
+        let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second["path"], "/data/b.txt");
+    }
+}
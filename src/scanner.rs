@@ -1,21 +1,120 @@
-use crate::state::{DirEntry, FileEntry, ScanState};
+use crate::state::{DirEntry, FileEntry, FileKind, ScanState};
+use crossbeam_channel::Sender;
+use glob::Pattern;
+use rayon::prelude::*;
 use std::fs;
 use std::io;
-use std::os::unix::fs::MetadataExt;
-use std::path::Path;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use walkdir::WalkDir;
 
+/// Current wall-clock time as (unix seconds, sub-second nanoseconds).
+fn now_unix() -> (i64, u32) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    (now.as_secs() as i64, now.subsec_nanos())
+}
+
+/// True when a `(mtime_sec, mtime_nsec)` timestamp is too close to the
+/// wall-clock instant `(obs_sec, obs_nsec)` at which the scanner observed it
+/// to trust a future "unchanged" comparison against it: either the
+/// filesystem reports whole-second resolution (`mtime_nsec == 0`) and the
+/// timestamp lands in the same second as the observation, or the timestamp
+/// is at-or-after the observation outright (a write racing the scan, or a
+/// clock coarser than we assumed). See `state::FileEntry::mtime_ambiguous`.
+fn is_second_ambiguous(mtime_sec: i64, mtime_nsec: u32, obs_sec: i64, obs_nsec: u32) -> bool {
+    (mtime_nsec == 0 && mtime_sec == obs_sec) || (mtime_sec, mtime_nsec) >= (obs_sec, obs_nsec)
+}
+
+/// Default `--hash-threshold`: files larger than this are never hashed, since
+/// verifying a large file's content on every rescan of its directory would
+/// erase the point of the mtime/size cache.
+pub const DEFAULT_HASH_THRESHOLD: u64 = 100 * 1024 * 1024;
+
+/// Hard ceiling on the scanning thread pool, independent of how many `--jobs`
+/// the caller asks for: unbounded fan-out against a network mount causes
+/// contention and latency spikes rather than speedups, which is why
+/// Mercurial's Rust status code ceilings its own thread count the same way.
+const MAX_SCAN_THREADS: usize = 16;
+
+/// Clamp a requested job count to `[1, min(available_parallelism, MAX_SCAN_THREADS)]`.
+fn capped_jobs(requested: usize) -> usize {
+    let available = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    requested.clamp(1, available.min(MAX_SCAN_THREADS))
+}
+
 pub struct ScanStats {
     pub dirs_cached: usize,
     pub dirs_scanned: usize,
     pub dirs_removed: usize,
 }
 
+/// A progress update emitted while a parallel scan is in flight, so a
+/// `--verbose` caller can print live counts instead of going silent on a
+/// large tree. See `scan_with_jobs`.
+#[derive(Debug, Clone)]
+pub struct ProgressData {
+    pub dirs_scanned: usize,
+    pub dirs_to_check: usize,
+    pub current_stage: String,
+}
+
+/// Scan `root` single-threaded. Equivalent to `scan_with_jobs(.., 1, None, false, DEFAULT_HASH_THRESHOLD, &[], false)`.
 pub fn scan(
     root: &Path,
     state: &mut ScanState,
     exclude: &[String],
     verbose: bool,
+) -> io::Result<ScanStats> {
+    scan_with_jobs(
+        root,
+        state,
+        exclude,
+        verbose,
+        1,
+        None,
+        false,
+        DEFAULT_HASH_THRESHOLD,
+        &[],
+        false,
+    )
+}
+
+/// Scan `root`, dispatching cache-miss directories across a pool of `jobs`
+/// worker threads, clamped to `[1, min(available_parallelism, MAX_SCAN_THREADS)]`
+/// (see `capped_jobs`) regardless of what `jobs` asks for — unbounded fan-out
+/// against a network mount causes contention rather than speedups. `WalkDir`
+/// traversal and cache-hit resolution stay on the calling thread (they're
+/// cheap); only the `read_dir` + per-file `metadata` work in `scan_directory`
+/// — the part that dominates wall time on high-latency mounts — runs in
+/// parallel. If `progress` is given, a `ProgressData` update is sent as each
+/// miss finishes. When `hash` is set, regular files under `hash_threshold`
+/// bytes whose size/mtime look unchanged from the previous scan are
+/// additionally verified by BLAKE3 content hash; see `FileEntry::content_hash`.
+///
+/// `ignore_patterns` is matched against each directory's and file's path
+/// relative to `root` (not just its basename), unifying with `exclude`'s
+/// exact-name matching to give the walk gitignore-like reach (`**/*.tmp`,
+/// `cache/**`). A matched directory is never descended into; by default it
+/// is pruned from the state entirely, but when `record_ignored_dirs` is set
+/// it is still recorded (with no files), so it shows up in output, following
+/// Mercurial's rule that ignored directories are only listed if opted into.
+pub fn scan_with_jobs(
+    root: &Path,
+    state: &mut ScanState,
+    exclude: &[String],
+    verbose: bool,
+    jobs: usize,
+    progress: Option<Sender<ProgressData>>,
+    hash: bool,
+    hash_threshold: u64,
+    ignore_patterns: &[Pattern],
+    record_ignored_dirs: bool,
 ) -> io::Result<ScanStats> {
     let mut stats = ScanStats {
         dirs_cached: 0,
@@ -24,36 +123,66 @@ pub fn scan(
     };
 
     let mut seen_dirs = std::collections::HashSet::new();
+    let mut misses: Vec<(String, PathBuf, PathBuf, i64, u32, bool, Option<DirEntry>)> = Vec::new();
 
-    let walker = WalkDir::new(root)
-        .sort_by_file_name()
-        .into_iter()
-        .filter_entry(|e| {
-            if e.file_type().is_dir()
-                && let Some(name) = e.path().file_name()
-            {
-                return !exclude
-                    .iter()
-                    .any(|ex| ex == name.to_string_lossy().as_ref());
-            }
-            true
-        });
+    let mut walker = WalkDir::new(root).sort_by_file_name().into_iter();
 
-    for entry in walker {
-        let entry = entry.map_err(io::Error::other)?;
+    loop {
+        let entry = match walker.next() {
+            None => break,
+            Some(Ok(entry)) => entry,
+            Some(Err(e)) => return Err(io::Error::other(e)),
+        };
         if !entry.file_type().is_dir() {
             continue;
         }
 
         let dir_path = entry.path().to_path_buf();
+
+        if let Some(name) = dir_path.file_name()
+            && exclude
+                .iter()
+                .any(|ex| ex == name.to_string_lossy().as_ref())
+        {
+            walker.skip_current_dir();
+            continue;
+        }
+
+        let dir_rel = dir_path.strip_prefix(root).unwrap_or(&dir_path).to_path_buf();
+        if !dir_rel.as_os_str().is_empty() && ignore_patterns.iter().any(|p| p.matches_path(&dir_rel)) {
+            walker.skip_current_dir();
+            if record_ignored_dirs {
+                let dir_key = dir_path.to_string_lossy().into_owned();
+                seen_dirs.insert(dir_key.clone());
+                let dir_meta = fs::metadata(&dir_path)?;
+                state.dirs.insert(
+                    dir_key,
+                    DirEntry {
+                        dir_mtime: dir_meta.mtime(),
+                        dir_mtime_nsec: dir_meta.mtime_nsec() as u32,
+                        files: Vec::new(),
+                        mtime_ambiguous: false,
+                    },
+                );
+            }
+            continue;
+        }
+
         let dir_key = dir_path.to_string_lossy().into_owned();
         seen_dirs.insert(dir_key.clone());
 
         let dir_meta = fs::metadata(&dir_path)?;
         let dir_mtime = dir_meta.mtime();
+        let dir_mtime_nsec = dir_meta.mtime_nsec() as u32;
+        let (obs_sec, obs_nsec) = now_unix();
+        let dir_ambiguous = is_second_ambiguous(dir_mtime, dir_mtime_nsec, obs_sec, obs_nsec);
 
-        if let Some(cached) = state.dirs.get(&dir_key)
+        let prev_entry = state.dirs.get(&dir_key);
+
+        if let Some(cached) = prev_entry
             && cached.dir_mtime == dir_mtime
+            && cached.dir_mtime_nsec == dir_mtime_nsec
+            && !cached.mtime_ambiguous
         {
             stats.dirs_cached += 1;
             if verbose {
@@ -62,13 +191,73 @@ pub fn scan(
             continue;
         }
 
-        stats.dirs_scanned += 1;
-        if verbose {
-            eprintln!("scanning: {}", dir_path.display());
-        }
+        let prev_entry = prev_entry.cloned();
+        misses.push((
+            dir_key,
+            dir_path,
+            dir_rel,
+            dir_mtime,
+            dir_mtime_nsec,
+            dir_ambiguous,
+            prev_entry,
+        ));
+    }
+
+    stats.dirs_scanned = misses.len();
+
+    if let Some(tx) = &progress {
+        let _ = tx.send(ProgressData {
+            dirs_scanned: 0,
+            dirs_to_check: misses.len(),
+            current_stage: "scanning".into(),
+        });
+    }
 
-        let files = scan_directory(&dir_path)?;
-        state.dirs.insert(dir_key, DirEntry { dir_mtime, files });
+    let completed = Arc::new(AtomicUsize::new(0));
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(capped_jobs(jobs))
+        .build()
+        .map_err(io::Error::other)?;
+
+    let results: Vec<io::Result<(String, i64, u32, bool, Vec<FileEntry>)>> = pool.install(|| {
+        misses
+            .par_iter()
+            .map(|(dir_key, dir_path, dir_rel, dir_mtime, dir_mtime_nsec, dir_ambiguous, prev_entry)| {
+                if verbose {
+                    eprintln!("scanning: {}", dir_path.display());
+                }
+                let files = scan_directory(
+                    dir_path,
+                    dir_rel,
+                    prev_entry.as_ref(),
+                    hash,
+                    hash_threshold,
+                    ignore_patterns,
+                )?;
+                let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+                if let Some(tx) = &progress {
+                    let _ = tx.send(ProgressData {
+                        dirs_scanned: done,
+                        dirs_to_check: misses.len(),
+                        current_stage: "scanning".into(),
+                    });
+                }
+                Ok((dir_key.clone(), *dir_mtime, *dir_mtime_nsec, *dir_ambiguous, files))
+            })
+            .collect()
+    });
+
+    for result in results {
+        let (dir_key, dir_mtime, dir_mtime_nsec, mtime_ambiguous, files) = result?;
+        state.dirs.insert(
+            dir_key,
+            DirEntry {
+                dir_mtime,
+                dir_mtime_nsec,
+                files,
+                mtime_ambiguous,
+            },
+        );
     }
 
     // Remove directories that no longer exist
@@ -89,26 +278,220 @@ pub fn scan(
     Ok(stats)
 }
 
-fn scan_directory(dir: &Path) -> io::Result<Vec<FileEntry>> {
+/// Process one directory entry into a `FileEntry`, or `Ok(None)` to skip it
+/// (ignored by a glob pattern, a directory, or vanished out from under us).
+/// Factored out of `scan_directory` so the vanished-entry path can be driven
+/// directly in tests instead of racing a background thread against it.
+fn scan_entry(
+    entry: fs::DirEntry,
+    dir_rel: &Path,
+    ignore_patterns: &[Pattern],
+    prev_files: &std::collections::HashMap<&str, &FileEntry>,
+    hash: bool,
+    hash_threshold: u64,
+    obs_sec: i64,
+    obs_nsec: u32,
+) -> io::Result<Option<FileEntry>> {
+    let filename = entry.file_name().to_string_lossy().into_owned();
+    if !ignore_patterns.is_empty()
+        && ignore_patterns
+            .iter()
+            .any(|p| p.matches_path(&dir_rel.join(&filename)))
+    {
+        return Ok(None);
+    }
+
+    // A file can be unlinked in the window between `read_dir` yielding this
+    // entry and our own `stat` of it (Mercurial calls this a status race
+    // against file deletion). Treat that as "never existed" for this scan
+    // rather than aborting the whole directory.
+    let ft = match entry.file_type() {
+        Ok(ft) => ft,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if ft.is_dir() {
+        return Ok(None);
+    }
+    let path = entry.path();
+    let meta = match entry.metadata() {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mtime = meta.mtime();
+    let mtime_nsec = meta.mtime_nsec() as u32;
+    let mtime_ambiguous = is_second_ambiguous(mtime, mtime_nsec, obs_sec, obs_nsec);
+    let kind = match classify_kind(&ft, &meta, &path) {
+        Ok(kind) => kind,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let xattrs = read_xattrs(&path);
+
+    let content_hash = if hash && kind == FileKind::Regular {
+        verify_or_hash(
+            &path,
+            meta.size(),
+            mtime,
+            mtime_nsec,
+            hash_threshold,
+            prev_files.get(filename.as_str()).copied(),
+        )?
+    } else {
+        None
+    };
+
+    Ok(Some(FileEntry {
+        filename,
+        size: meta.size(),
+        ctime: meta.ctime(),
+        mtime,
+        mtime_nsec,
+        mode: meta.mode(),
+        mtime_ambiguous,
+        kind,
+        xattrs,
+        content_hash,
+    }))
+}
+
+/// Scan one directory's immediate children into `FileEntry`s. Entries whose
+/// path relative to the scan root (`dir_rel` joined with the filename)
+/// matches one of `ignore_patterns` are skipped outright. Also tolerates
+/// entries that vanish between `read_dir` listing them and our own `stat`
+/// of them (a file deleted mid-scan) by skipping them rather than failing
+/// the whole directory; any other error still propagates. See `scan_entry`
+/// for the per-entry logic.
+fn scan_directory(
+    dir: &Path,
+    dir_rel: &Path,
+    prev: Option<&DirEntry>,
+    hash: bool,
+    hash_threshold: u64,
+    ignore_patterns: &[Pattern],
+) -> io::Result<Vec<FileEntry>> {
+    // One observation instant for every file in this directory: a file
+    // written between two different `now_unix()` calls could otherwise be
+    // judged ambiguous against one sibling's observation but not another's.
+    let (obs_sec, obs_nsec) = now_unix();
+    let prev_files: std::collections::HashMap<&str, &FileEntry> = prev
+        .map(|d| d.files.iter().map(|f| (f.filename.as_str(), f)).collect())
+        .unwrap_or_default();
+
     let mut files = Vec::new();
     for entry in fs::read_dir(dir)? {
-        let entry = entry?;
-        let ft = entry.file_type()?;
-        if !ft.is_file() {
-            continue;
+        if let Some(file) = scan_entry(
+            entry?,
+            dir_rel,
+            ignore_patterns,
+            &prev_files,
+            hash,
+            hash_threshold,
+            obs_sec,
+            obs_nsec,
+        )? {
+            files.push(file);
         }
-        let meta = entry.metadata()?;
-        files.push(FileEntry {
-            filename: entry.file_name().to_string_lossy().into_owned(),
-            size: meta.size(),
-            ctime: meta.ctime(),
-            mtime: meta.mtime(),
-        });
     }
     files.sort_by(|a, b| a.filename.cmp(&b.filename));
     Ok(files)
 }
 
+/// Verify a file's content against its previously cached hash when size and
+/// mtime look unchanged from the prior scan, since that's exactly the case a
+/// `touch`-style restore or a build tool replaying timestamps can spoof.
+/// Skips hashing (returning `None`) when there's no prior entry to verify
+/// against or the file exceeds `hash_threshold`, to keep `--hash` affordable
+/// on large trees.
+fn verify_or_hash(
+    path: &Path,
+    size: u64,
+    mtime: i64,
+    mtime_nsec: u32,
+    hash_threshold: u64,
+    prev: Option<&FileEntry>,
+) -> io::Result<Option<[u8; 32]>> {
+    let Some(prev) = prev else {
+        return Ok(None);
+    };
+    if prev.size != size || prev.mtime != mtime || prev.mtime_nsec != mtime_nsec {
+        return Ok(None);
+    }
+    if size > hash_threshold {
+        return Ok(None);
+    }
+
+    // Same status race as the rest of `scan_directory`: the file can vanish
+    // between our `metadata()` stat and this read, so a NotFound here means
+    // "no hash" rather than aborting the scan.
+    let digest = match hash_file(path) {
+        Ok(digest) => digest,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if let Some(prev_hash) = prev.content_hash
+        && prev_hash != digest
+    {
+        eprintln!(
+            "warning: {} changed content despite unchanged size/mtime (possible touch-restore)",
+            path.display()
+        );
+    }
+    Ok(Some(digest))
+}
+
+/// BLAKE3 digest of a file's content, hashed incrementally off a buffered
+/// reader rather than loading the whole file into memory.
+fn hash_file(path: &Path) -> io::Result<[u8; 32]> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(&mut file)?;
+    Ok(*hasher.finalize().as_bytes())
+}
+
+/// Classify a directory entry's filesystem type — symlink (with its target),
+/// FIFO, socket, block/char device (with `rdev`), or plain file — so backup-style
+/// consumers can tell special files apart instead of seeing everything as regular.
+/// Anything none of `FileType`'s predicates recognize falls back to `Unknown`
+/// rather than being silently misreported as `Regular`.
+fn classify_kind(ft: &fs::FileType, meta: &fs::Metadata, path: &Path) -> io::Result<FileKind> {
+    if ft.is_symlink() {
+        let target = fs::read_link(path)?.to_string_lossy().into_owned();
+        return Ok(FileKind::Symlink { target });
+    }
+    if ft.is_fifo() {
+        return Ok(FileKind::Fifo);
+    }
+    if ft.is_socket() {
+        return Ok(FileKind::Socket);
+    }
+    if ft.is_block_device() {
+        return Ok(FileKind::BlockDevice { rdev: meta.rdev() });
+    }
+    if ft.is_char_device() {
+        return Ok(FileKind::CharDevice { rdev: meta.rdev() });
+    }
+    if ft.is_file() {
+        return Ok(FileKind::Regular);
+    }
+    Ok(FileKind::Unknown)
+}
+
+/// Read all extended attributes for `path`, returning an empty list on
+/// filesystems or entries that don't support them rather than erroring the scan.
+fn read_xattrs(path: &Path) -> Vec<(String, Vec<u8>)> {
+    let Ok(names) = xattr::list(path) else {
+        return Vec::new();
+    };
+    names
+        .filter_map(|name| {
+            let value = xattr::get(path, &name).ok().flatten()?;
+            Some((name.to_string_lossy().into_owned(), value))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -179,6 +562,60 @@ mod tests {
         assert_eq!(stats.dirs_removed, 0);
     }
 
+    #[test]
+    fn ambiguous_dir_is_always_rescanned() {
+        let tmp = tempfile::tempdir().unwrap();
+        make_tree(tmp.path());
+
+        let mut state = ScanState::default();
+        scan(tmp.path(), &mut state, &[], false).unwrap();
+
+        // Simulate a same-second write: the mtime matches, but the entry is
+        // flagged ambiguous (as `scan` itself does via `is_second_ambiguous`
+        // when the dir's mtime is too close to the instant it was observed).
+        let sub_key = key(&tmp.path().join("sub"));
+        state.dirs.get_mut(&sub_key).unwrap().mtime_ambiguous = true;
+
+        let stats = scan(tmp.path(), &mut state, &[], false).unwrap();
+        // sub/ must be forced to rescan despite its mtime matching.
+        assert_eq!(stats.dirs_scanned, 1);
+        assert_eq!(stats.dirs_cached, 2);
+    }
+
+    #[test]
+    fn a_directory_whose_mtime_races_the_scan_is_flagged_and_always_rescanned() {
+        // Exercises the real `is_second_ambiguous` "at-or-after observation"
+        // branch end to end, instead of flipping `mtime_ambiguous` by hand:
+        // force `sub/`'s mtime a few seconds into the future so it's
+        // unmistakably at-or-after whatever instant `scan()` observes it at,
+        // the same situation a write landing in the same wall-clock second as
+        // the scan would produce.
+        let tmp = tempfile::tempdir().unwrap();
+        make_tree(tmp.path());
+
+        let sub_path = tmp.path().join("sub");
+        let future = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::File::open(&sub_path)
+            .unwrap()
+            .set_modified(future)
+            .unwrap();
+
+        let mut state = ScanState::default();
+        scan(tmp.path(), &mut state, &[], false).unwrap();
+
+        let sub_key = key(&sub_path);
+        assert!(
+            state.dirs[&sub_key].mtime_ambiguous,
+            "a directory mtime at-or-after the scan's own observation must be flagged ambiguous"
+        );
+
+        // Nothing changes on disk between scans, so without the ambiguity
+        // flag this would be a cache hit; with it, sub/ must be rescanned.
+        let stats = scan(tmp.path(), &mut state, &[], false).unwrap();
+        assert_eq!(stats.dirs_scanned, 1);
+        assert_eq!(stats.dirs_cached, 2);
+    }
+
     #[test]
     fn remove_subdir_shows_removed() {
         let tmp = tempfile::tempdir().unwrap();
@@ -221,6 +658,76 @@ mod tests {
         assert_eq!(stats.dirs_scanned, 3);
     }
 
+    #[test]
+    fn parallel_scan_matches_serial_scan() {
+        let tmp = tempfile::tempdir().unwrap();
+        make_tree(tmp.path());
+
+        let mut state = ScanState::default();
+        let stats = scan_with_jobs(tmp.path(), &mut state, &[], false, 4, None, false, DEFAULT_HASH_THRESHOLD, &[], false).unwrap();
+
+        assert_eq!(stats.dirs_scanned, 3);
+        assert_eq!(stats.dirs_cached, 0);
+        assert_eq!(state.dirs.len(), 3);
+        let entry = &state.dirs[&key(tmp.path())];
+        assert_eq!(entry.files.len(), 1);
+        assert_eq!(entry.files[0].filename, "a.txt");
+    }
+
+    #[test]
+    fn parallel_scan_reports_progress() {
+        let tmp = tempfile::tempdir().unwrap();
+        make_tree(tmp.path());
+
+        let mut state = ScanState::default();
+        let (tx, rx) = crossbeam_channel::unbounded();
+        scan_with_jobs(tmp.path(), &mut state, &[], false, 4, Some(tx), false, DEFAULT_HASH_THRESHOLD, &[], false).unwrap();
+
+        let updates: Vec<_> = rx.try_iter().collect();
+        assert!(!updates.is_empty());
+        // Workers count and send separately, so updates can arrive out of
+        // order; the highest count reported must still reach the total.
+        let max_scanned = updates.iter().map(|u| u.dirs_scanned).max().unwrap();
+        assert_eq!(max_scanned, updates[0].dirs_to_check);
+    }
+
+    #[test]
+    fn capped_jobs_never_exceeds_the_hard_ceiling() {
+        assert!(capped_jobs(usize::MAX) <= MAX_SCAN_THREADS);
+        assert!(capped_jobs(1000) <= MAX_SCAN_THREADS);
+    }
+
+    #[test]
+    fn capped_jobs_never_returns_zero() {
+        assert!(capped_jobs(0) >= 1);
+    }
+
+    #[test]
+    fn scan_with_an_excessive_job_count_still_completes() {
+        // A caller asking for an absurd thread count shouldn't fail or spawn
+        // an unbounded pool; it should silently clamp to the hard ceiling.
+        let tmp = tempfile::tempdir().unwrap();
+        make_tree(tmp.path());
+
+        let mut state = ScanState::default();
+        let stats = scan_with_jobs(
+            tmp.path(),
+            &mut state,
+            &[],
+            false,
+            1000,
+            None,
+            false,
+            DEFAULT_HASH_THRESHOLD,
+            &[],
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(stats.dirs_scanned, 3);
+        assert_eq!(state.dirs.len(), 3);
+    }
+
     #[test]
     fn files_sorted_by_filename() {
         let tmp = tempfile::tempdir().unwrap();
@@ -237,6 +744,26 @@ mod tests {
         assert_eq!(names, vec!["a.txt", "m.txt", "z.txt"]);
     }
 
+    #[test]
+    fn second_ambiguous_rule_same_second_whole_second_mtime() {
+        // Whole-second filesystem (mtime_nsec == 0) landing in the same
+        // second as the observation is ambiguous...
+        assert!(is_second_ambiguous(1_700_000_000, 0, 1_700_000_000, 500_000_000));
+        // ...but a whole-second mtime from an earlier second is not.
+        assert!(!is_second_ambiguous(1_699_999_999, 0, 1_700_000_000, 0));
+    }
+
+    #[test]
+    fn second_ambiguous_rule_at_or_after_observation() {
+        // A nanosecond-precision mtime at or after the observed instant is
+        // ambiguous (could be a write racing the scan, or a clock we
+        // mis-trusted the granularity of).
+        assert!(is_second_ambiguous(1_700_000_000, 999, 1_700_000_000, 0));
+        assert!(is_second_ambiguous(1_700_000_001, 0, 1_700_000_000, 999));
+        // Comfortably in the past is not ambiguous.
+        assert!(!is_second_ambiguous(1_699_999_000, 999_999_999, 1_700_000_000, 0));
+    }
+
     #[test]
     fn empty_directory_produces_empty_files() {
         let tmp = tempfile::tempdir().unwrap();
@@ -247,4 +774,221 @@ mod tests {
         let entry = &state.dirs[&key(tmp.path())];
         assert!(entry.files.is_empty());
     }
+
+    #[test]
+    fn symlinks_recorded_with_target() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("real.txt"), "hi").unwrap();
+        symlink("real.txt", tmp.path().join("link.txt")).unwrap();
+
+        let mut state = ScanState::default();
+        scan(tmp.path(), &mut state, &[], false).unwrap();
+
+        let entry = &state.dirs[&key(tmp.path())];
+        let link = entry
+            .files
+            .iter()
+            .find(|f| f.filename == "link.txt")
+            .unwrap();
+        assert_eq!(
+            link.kind,
+            FileKind::Symlink {
+                target: "real.txt".into()
+            }
+        );
+        let real = entry
+            .files
+            .iter()
+            .find(|f| f.filename == "real.txt")
+            .unwrap();
+        assert_eq!(real.kind, FileKind::Regular);
+    }
+
+    #[test]
+    fn fifos_are_recorded_with_fifo_kind() {
+        let tmp = tempfile::tempdir().unwrap();
+        let fifo_path = tmp.path().join("pipe");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo_path)
+            .status()
+            .unwrap();
+        assert!(status.success(), "mkfifo must be available for this test");
+
+        let mut state = ScanState::default();
+        scan(tmp.path(), &mut state, &[], false).unwrap();
+
+        let entry = &state.dirs[&key(tmp.path())];
+        let pipe = entry.files.iter().find(|f| f.filename == "pipe").unwrap();
+        assert_eq!(pipe.kind, FileKind::Fifo);
+    }
+
+    #[test]
+    fn a_file_deleted_mid_scan_is_skipped_instead_of_aborting_the_scan() {
+        // scan_entry's entry.metadata() call can race a concurrent deletion
+        // (Mercurial calls this a status race against file deletion).
+        // Reproduce it deterministically by taking the same fs::DirEntry
+        // scan_directory would see, deleting the file out from under it, and
+        // feeding that now-stale entry straight to scan_entry -- no thread
+        // or sleep needed to land the race.
+        let tmp = tempfile::tempdir().unwrap();
+        let victim = tmp.path().join("victim.txt");
+        fs::write(&victim, "x").unwrap();
+
+        let entry = fs::read_dir(tmp.path())
+            .unwrap()
+            .map(|e| e.unwrap())
+            .find(|e| e.file_name() == "victim.txt")
+            .unwrap();
+        fs::remove_file(&victim).unwrap();
+
+        let result = scan_entry(
+            entry,
+            Path::new(""),
+            &[],
+            &std::collections::HashMap::new(),
+            false,
+            DEFAULT_HASH_THRESHOLD,
+            0,
+            0,
+        )
+        .unwrap();
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn hash_is_not_computed_without_a_prior_entry() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("a.txt"), "hello").unwrap();
+
+        let mut state = ScanState::default();
+        scan_with_jobs(tmp.path(), &mut state, &[], false, 1, None, true, DEFAULT_HASH_THRESHOLD, &[], false)
+            .unwrap();
+
+        let entry = &state.dirs[&key(tmp.path())];
+        let a = entry.files.iter().find(|f| f.filename == "a.txt").unwrap();
+        assert_eq!(a.content_hash, None);
+    }
+
+    #[test]
+    fn hash_is_verified_once_a_prior_entry_exists() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("a.txt"), "hello").unwrap();
+
+        let mut state = ScanState::default();
+        scan_with_jobs(tmp.path(), &mut state, &[], false, 1, None, true, DEFAULT_HASH_THRESHOLD, &[], false)
+            .unwrap();
+
+        // Adding a sibling bumps the directory's own mtime, forcing a rescan
+        // even though a.txt's own size/mtime are untouched.
+        fs::write(tmp.path().join("b.txt"), "world").unwrap();
+        scan_with_jobs(tmp.path(), &mut state, &[], false, 1, None, true, DEFAULT_HASH_THRESHOLD, &[], false)
+            .unwrap();
+
+        let entry = &state.dirs[&key(tmp.path())];
+        let a = entry.files.iter().find(|f| f.filename == "a.txt").unwrap();
+        assert_eq!(a.content_hash, Some(blake3::hash(b"hello").into()));
+    }
+
+    #[test]
+    fn hash_threshold_skips_large_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("a.txt"), "hello").unwrap();
+
+        let mut state = ScanState::default();
+        scan_with_jobs(tmp.path(), &mut state, &[], false, 1, None, true, 0, &[], false).unwrap();
+
+        fs::write(tmp.path().join("b.txt"), "world").unwrap();
+        scan_with_jobs(tmp.path(), &mut state, &[], false, 1, None, true, 0, &[], false).unwrap();
+
+        let entry = &state.dirs[&key(tmp.path())];
+        let a = entry.files.iter().find(|f| f.filename == "a.txt").unwrap();
+        assert_eq!(a.content_hash, None);
+    }
+
+    #[test]
+    fn ignore_pattern_excludes_matching_files_from_the_directory() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("keep.txt"), "keep").unwrap();
+        fs::write(tmp.path().join("scratch.tmp"), "junk").unwrap();
+
+        let patterns = vec![Pattern::new("*.tmp").unwrap()];
+        let mut state = ScanState::default();
+        scan_with_jobs(
+            tmp.path(),
+            &mut state,
+            &[],
+            false,
+            1,
+            None,
+            false,
+            DEFAULT_HASH_THRESHOLD,
+            &patterns,
+            false,
+        )
+        .unwrap();
+
+        let entry = &state.dirs[&key(tmp.path())];
+        let names: Vec<&str> = entry.files.iter().map(|f| f.filename.as_str()).collect();
+        assert_eq!(names, vec!["keep.txt"]);
+    }
+
+    #[test]
+    fn ignore_pattern_prunes_a_directory_without_descending_into_it() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("cache/nested")).unwrap();
+        fs::write(tmp.path().join("cache/nested/file.txt"), "x").unwrap();
+        fs::write(tmp.path().join("kept.txt"), "x").unwrap();
+
+        let patterns = vec![Pattern::new("cache").unwrap()];
+        let mut state = ScanState::default();
+        let stats = scan_with_jobs(
+            tmp.path(),
+            &mut state,
+            &[],
+            false,
+            1,
+            None,
+            false,
+            DEFAULT_HASH_THRESHOLD,
+            &patterns,
+            false,
+        )
+        .unwrap();
+
+        // Only the root is scanned: cache/ and cache/nested/ are pruned
+        // entirely, by default, without being recorded.
+        assert_eq!(stats.dirs_scanned, 1);
+        assert!(!state.dirs.contains_key(&key(&tmp.path().join("cache"))));
+        assert!(!state.dirs.contains_key(&key(&tmp.path().join("cache/nested"))));
+    }
+
+    #[test]
+    fn record_ignored_dirs_keeps_an_empty_entry_for_an_ignored_directory() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("cache")).unwrap();
+        fs::write(tmp.path().join("cache/file.txt"), "x").unwrap();
+
+        let patterns = vec![Pattern::new("cache").unwrap()];
+        let mut state = ScanState::default();
+        scan_with_jobs(
+            tmp.path(),
+            &mut state,
+            &[],
+            false,
+            1,
+            None,
+            false,
+            DEFAULT_HASH_THRESHOLD,
+            &patterns,
+            true,
+        )
+        .unwrap();
+
+        let cache_key = key(&tmp.path().join("cache"));
+        let entry = state.dirs.get(&cache_key).expect("ignored dir recorded");
+        assert!(entry.files.is_empty());
+    }
 }
@@ -1,8 +1,13 @@
-use crate::state::ScanState;
+use crate::gitignore::IgnoreStack;
+use crate::state::{FileKind, ScanState};
 use glob::Pattern;
 use icu_collator::CollatorBorrowed;
 use icu_collator::options::{AlternateHandling, CollatorOptions, Strength};
-use std::collections::{BTreeMap, BTreeSet};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
+use std::io;
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 
 /// Escape non-printable and non-ASCII bytes to '?' (matching tree's default behavior).
@@ -30,6 +35,14 @@ struct TreeContext<'a> {
     collator: CollatorBorrowed<'static>,
     no_escape: bool,
     show_hidden: bool,
+    /// Memoized aggregate sizes for `--du` mode, keyed by directory path.
+    du_sizes: Option<HashMap<PathBuf, u64>>,
+    /// Whether `--gitignore` is in effect; when true, each directory's own
+    /// `.gitignore` (if present) is loaded and layered onto the stack as we descend.
+    use_gitignore: bool,
+    /// `LS_COLORS`-derived lookup (`di`, `ex`, `*.ext` → SGR code), present only
+    /// when `--color` resolved to on for this run.
+    colors: Option<HashMap<String, String>>,
 }
 
 /// Render a tree view of the scan state, printing to stdout.
@@ -40,8 +53,56 @@ pub fn render_tree(
     patterns: &[Pattern],
     no_escape: bool,
     show_hidden: bool,
+    use_gitignore: bool,
+    use_color: bool,
 ) -> (usize, usize) {
-    // Build child-directory map: for each dir in state, register it as a child of its parent
+    render_tree_inner(
+        state,
+        root,
+        patterns,
+        no_escape,
+        show_hidden,
+        false,
+        use_gitignore,
+        use_color,
+    )
+}
+
+/// Render a disk-usage tree: same layout as `render_tree`, but entries are
+/// annotated with their aggregate size and sorted largest-first.
+pub fn render_tree_du(
+    state: &ScanState,
+    root: &Path,
+    patterns: &[Pattern],
+    no_escape: bool,
+    show_hidden: bool,
+    use_gitignore: bool,
+    use_color: bool,
+) -> (usize, usize) {
+    render_tree_inner(
+        state,
+        root,
+        patterns,
+        no_escape,
+        show_hidden,
+        true,
+        use_gitignore,
+        use_color,
+    )
+}
+
+/// Emit the scan state as a machine-readable JSON tree instead of ASCII art:
+/// a recursive `{ "type": "directory"|"file", "name", "contents" }` document
+/// mirroring `render_dir`'s recursion (honoring the same ignore patterns,
+/// hidden-file filtering, and collator ordering), followed by a trailing
+/// `{ "type": "report", "directories", "files" }` object.
+pub fn render_tree_json(
+    state: &ScanState,
+    root: &Path,
+    patterns: &[Pattern],
+    show_hidden: bool,
+    use_gitignore: bool,
+) -> io::Result<()> {
     let mut children: BTreeMap<PathBuf, BTreeSet<String>> = BTreeMap::new();
     for dir_key in state.dirs.keys() {
         let dir_path = Path::new(dir_key);
@@ -61,22 +122,309 @@ pub fn render_tree(
     let collator = CollatorBorrowed::try_new(Default::default(), options).unwrap();
 
     let ctx = TreeContext {
+        state,
+        children,
+        patterns,
+        collator,
+        no_escape: true,
+        show_hidden,
+        du_sizes: None,
+        use_gitignore,
+        colors: None,
+    };
+
+    let ignore_stack = IgnoreStack::new();
+    let ignore_stack = if use_gitignore {
+        ignore_stack.push(root)
+    } else {
+        ignore_stack
+    };
+
+    let mut dir_count = 1; // count the root directory itself, matching tree's behavior
+    let mut file_count = 0;
+    let node = build_json_node(
+        &ctx,
+        root,
+        root.display().to_string(),
+        &mut dir_count,
+        &mut file_count,
+        &ignore_stack,
+    );
+
+    serde_json::to_writer(io::stdout(), &node)?;
+    println!();
+    serde_json::to_writer(
+        io::stdout(),
+        &json!({"type": "report", "directories": dir_count, "files": file_count}),
+    )?;
+    println!();
+    Ok(())
+}
+
+/// Recursively build the JSON node for `dir_path`, named `name`, counting
+/// directories/files the same way `render_dir` does.
+fn build_json_node(
+    ctx: &TreeContext,
+    dir_path: &Path,
+    name: String,
+    dir_count: &mut usize,
+    file_count: &mut usize,
+    ignore_stack: &IgnoreStack,
+) -> serde_json::Value {
+    let dir_key = dir_path.to_string_lossy();
+    let files: Vec<String> = ctx
+        .state
+        .dirs
+        .get(dir_key.as_ref())
+        .map(|d| d.files.iter().map(|f| f.filename.clone()).collect())
+        .unwrap_or_default();
+    let empty = BTreeSet::new();
+    let child_dirs = ctx.children.get(dir_path).unwrap_or(&empty);
+
+    let entries = merge_entries(&files, child_dirs, ctx, dir_path, ignore_stack);
+    let contents: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|entry| match entry {
+            Entry::File(name) => {
+                *file_count += 1;
+                let kind = entry_kind(ctx, dir_path, entry);
+                match &kind {
+                    Some(FileKind::Symlink { target }) => {
+                        json!({"type": "file", "name": name, "kind": "symlink", "target": target})
+                    }
+                    Some(k) => json!({"type": "file", "name": name, "kind": k.label()}),
+                    None => json!({"type": "file", "name": name, "kind": "file"}),
+                }
+            }
+            Entry::Dir(name) => {
+                *dir_count += 1;
+                let child_path = dir_path.join(name);
+                let child_stack = if ctx.use_gitignore {
+                    ignore_stack.push(&child_path)
+                } else {
+                    ignore_stack.clone()
+                };
+                build_json_node(
+                    ctx,
+                    &child_path,
+                    name.clone(),
+                    dir_count,
+                    file_count,
+                    &child_stack,
+                )
+            }
+        })
+        .collect();
+
+    json!({"type": "directory", "name": name, "contents": contents})
+}
+
+fn render_tree_inner(
+    state: &ScanState,
+    root: &Path,
+    patterns: &[Pattern],
+    no_escape: bool,
+    show_hidden: bool,
+    du: bool,
+    use_gitignore: bool,
+    use_color: bool,
+) -> (usize, usize) {
+    // Build child-directory map: for each dir in state, register it as a child of its parent
+    let mut children: BTreeMap<PathBuf, BTreeSet<String>> = BTreeMap::new();
+    for dir_key in state.dirs.keys() {
+        let dir_path = Path::new(dir_key);
+        if let Some(parent) = dir_path.parent()
+            && let Some(name) = dir_path.file_name()
+        {
+            children
+                .entry(parent.to_path_buf())
+                .or_default()
+                .insert(name.to_string_lossy().into_owned());
+        }
+    }
+
+    let mut options = CollatorOptions::default();
+    options.strength = Some(Strength::Quaternary);
+    options.alternate_handling = Some(AlternateHandling::Shifted);
+    let collator = CollatorBorrowed::try_new(Default::default(), options).unwrap();
+
+    let mut ctx = TreeContext {
         state,
         children,
         patterns,
         collator,
         no_escape,
         show_hidden,
+        du_sizes: None,
+        use_gitignore,
+        colors: use_color.then(parse_ls_colors),
+    };
+
+    if du {
+        let mut memo = HashMap::new();
+        aggregate_size(&ctx, root, &mut memo);
+        ctx.du_sizes = Some(memo);
+    }
+
+    let ignore_stack = IgnoreStack::new();
+    let ignore_stack = if use_gitignore {
+        ignore_stack.push(root)
+    } else {
+        ignore_stack
     };
 
     println!("{}", root.display());
 
     let mut dir_count = 1; // count the root directory itself, matching tree's behavior
     let mut file_count = 0;
-    render_dir(&ctx, root, "", &mut dir_count, &mut file_count);
+    let root_size = ctx.du_sizes.as_ref().and_then(|m| m.get(root)).copied();
+    let columns = terminal_width();
+    render_dir(
+        &ctx,
+        root,
+        "",
+        &mut dir_count,
+        &mut file_count,
+        root_size,
+        columns,
+        &ignore_stack,
+    );
     (dir_count, file_count)
 }
 
+/// Recursively sum a directory's own file sizes plus its subdirectories' aggregates,
+/// memoizing results since `render_dir` (and callers) may revisit the same directory.
+fn aggregate_size(ctx: &TreeContext, dir_path: &Path, memo: &mut HashMap<PathBuf, u64>) -> u64 {
+    if let Some(&cached) = memo.get(dir_path) {
+        return cached;
+    }
+
+    let own: u64 = ctx
+        .state
+        .dirs
+        .get(dir_path.to_string_lossy().as_ref())
+        .map(|d| d.files.iter().map(|f| f.size).sum())
+        .unwrap_or(0);
+
+    let empty = BTreeSet::new();
+    let children_total: u64 = ctx
+        .children
+        .get(dir_path)
+        .unwrap_or(&empty)
+        .iter()
+        .map(|name| aggregate_size(ctx, &dir_path.join(name), memo))
+        .sum();
+
+    let total = own + children_total;
+    memo.insert(dir_path.to_path_buf(), total);
+    total
+}
+
+/// Format a byte count using 1024-based units, e.g. `12.4M`.
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes}B")
+    } else {
+        format!("{size:.1}{}", UNITS[unit])
+    }
+}
+
+/// Draw a proportional `[####    ]`-style bar for `size` relative to `scale`.
+fn size_bar(size: u64, scale: u64, width: usize) -> String {
+    if width == 0 || scale == 0 {
+        return String::new();
+    }
+    let filled = ((size as f64 / scale as f64) * width as f64).round() as usize;
+    let filled = filled.min(width);
+    format!("[{}{}]", "#".repeat(filled), " ".repeat(width - filled))
+}
+
+/// Terminal width in columns, falling back to 80 when stdout isn't a TTY.
+fn terminal_width() -> usize {
+    if !std::io::stdout().is_terminal() {
+        return 80;
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(80)
+}
+
+/// Whether `--color` should resolve to on: `auto` only colorizes when stdout is a TTY.
+pub fn resolve_color(mode: &str) -> bool {
+    match mode {
+        "always" => true,
+        "never" => false,
+        _ => std::io::stdout().is_terminal(),
+    }
+}
+
+/// Parse the `LS_COLORS` environment variable (`key=sgr` pairs separated by `:`,
+/// e.g. `di=01;34:*.tar=01;31`) into a lookup table, falling back to a minimal
+/// built-in default (directories, executables) for keys it doesn't define.
+fn parse_ls_colors() -> HashMap<String, String> {
+    let raw = env::var("LS_COLORS").unwrap_or_default();
+    let mut map: HashMap<String, String> = raw
+        .split(':')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    map.entry("di".to_string()).or_insert_with(|| "01;34".to_string());
+    map.entry("ex".to_string()).or_insert_with(|| "01;32".to_string());
+    map.entry("ln".to_string()).or_insert_with(|| "01;36".to_string());
+    map.entry("pi".to_string()).or_insert_with(|| "33".to_string());
+    map.entry("so".to_string()).or_insert_with(|| "01;35".to_string());
+    map.entry("bd".to_string()).or_insert_with(|| "01;33".to_string());
+    map.entry("cd".to_string()).or_insert_with(|| "01;33".to_string());
+    map
+}
+
+/// Look up the SGR code for an entry: directories use `di`; symlinks, FIFOs,
+/// sockets, and device nodes use their respective `ln`/`pi`/`so`/`bd`/`cd` keys;
+/// files with any executable bit set use `ex`; otherwise the extension is
+/// matched against `*.ext`.
+fn color_for<'a>(
+    colors: &'a HashMap<String, String>,
+    name: &str,
+    is_dir: bool,
+    mode: u32,
+    kind: Option<&FileKind>,
+) -> Option<&'a str> {
+    if is_dir {
+        return colors.get("di").map(String::as_str);
+    }
+    match kind {
+        Some(FileKind::Symlink { .. }) => return colors.get("ln").map(String::as_str),
+        Some(FileKind::Fifo) => return colors.get("pi").map(String::as_str),
+        Some(FileKind::Socket) => return colors.get("so").map(String::as_str),
+        Some(FileKind::BlockDevice { .. }) => return colors.get("bd").map(String::as_str),
+        Some(FileKind::CharDevice { .. }) => return colors.get("cd").map(String::as_str),
+        _ => {}
+    }
+    if mode & 0o111 != 0 {
+        return colors.get("ex").map(String::as_str);
+    }
+    Path::new(name)
+        .extension()
+        .and_then(|ext| colors.get(&format!("*.{}", ext.to_string_lossy())))
+        .map(String::as_str)
+}
+
+/// Wrap `name` in the ANSI escapes for `code`, or return it unchanged if `code` is `None`.
+fn colorize(name: &str, code: Option<&str>) -> String {
+    match code {
+        Some(code) => format!("\x1b[{code}m{name}\x1b[0m"),
+        None => name.to_string(),
+    }
+}
+
 /// Entry in the merged directory listing — either a file or subdirectory.
 enum Entry {
     File(String),
@@ -91,7 +439,76 @@ impl Entry {
     }
 }
 
-fn merge_entries(files: &[String], child_dirs: &BTreeSet<String>, ctx: &TreeContext) -> Vec<Entry> {
+/// Aggregate size of an entry under `dir_path`, used for `--du` sorting/display.
+/// Files are looked up in the directory's cached `FileEntry` list; subdirectories
+/// use the memoized `aggregate_size` totals.
+fn entry_size(ctx: &TreeContext, dir_path: &Path, entry: &Entry) -> u64 {
+    match entry {
+        Entry::File(name) => ctx
+            .state
+            .dirs
+            .get(dir_path.to_string_lossy().as_ref())
+            .and_then(|d| d.files.iter().find(|f| &f.filename == name))
+            .map(|f| f.size)
+            .unwrap_or(0),
+        Entry::Dir(name) => ctx
+            .du_sizes
+            .as_ref()
+            .and_then(|m| m.get(&dir_path.join(name)))
+            .copied()
+            .unwrap_or(0),
+    }
+}
+
+/// Raw `st_mode` bits for a file entry under `dir_path`, used to classify
+/// executables for `--color`. Directories have no stored mode; callers branch
+/// on `Entry::Dir` before consulting this.
+fn entry_mode(ctx: &TreeContext, dir_path: &Path, entry: &Entry) -> u32 {
+    match entry {
+        Entry::File(name) => ctx
+            .state
+            .dirs
+            .get(dir_path.to_string_lossy().as_ref())
+            .and_then(|d| d.files.iter().find(|f| &f.filename == name))
+            .map(|f| f.mode)
+            .unwrap_or(0),
+        Entry::Dir(_) => 0,
+    }
+}
+
+/// Filesystem kind for a file entry under `dir_path`, used to color
+/// symlinks/devices/special files under `--color`, to append a symlink's
+/// `-> target` (with or without color), and for the JSON `kind` field.
+/// Directories have no stored `FileKind`; this returns `None` for `Entry::Dir`.
+fn entry_kind(ctx: &TreeContext, dir_path: &Path, entry: &Entry) -> Option<FileKind> {
+    match entry {
+        Entry::File(name) => ctx
+            .state
+            .dirs
+            .get(dir_path.to_string_lossy().as_ref())
+            .and_then(|d| d.files.iter().find(|f| &f.filename == name))
+            .map(|f| f.kind.clone()),
+        Entry::Dir(_) => None,
+    }
+}
+
+/// Merge `dir_path`'s files and child directories into one sorted listing,
+/// dropping hidden entries (unless `--all`), entries matching `ctx.patterns`,
+/// and, under `--gitignore`, entries `ignore_stack` considers ignored.
+///
+/// A directory excluded by `ignore_stack` is dropped here and never recursed
+/// into by `render_dir`/`build_json_node`, so its own `.gitignore` (with any
+/// `!`-negations) is never loaded onto the stack. This is intentional and
+/// matches git itself: a `!`-negation cannot re-include anything beneath an
+/// excluded parent directory. See `IgnoreStack`'s doc comment for the same
+/// rationale.
+fn merge_entries(
+    files: &[String],
+    child_dirs: &BTreeSet<String>,
+    ctx: &TreeContext,
+    dir_path: &Path,
+    ignore_stack: &IgnoreStack,
+) -> Vec<Entry> {
     let mut entries: Vec<Entry> = files
         .iter()
         .map(|f| Entry::File(f.clone()))
@@ -101,11 +518,28 @@ fn merge_entries(files: &[String], child_dirs: &BTreeSet<String>, ctx: &TreeCont
             if !ctx.show_hidden && n.starts_with('.') {
                 return false;
             }
-            !ctx.patterns.iter().any(|p| p.matches(n))
+            if ctx.patterns.iter().any(|p| p.matches(n)) {
+                return false;
+            }
+            if ctx.use_gitignore {
+                let is_dir = matches!(e, Entry::Dir(_));
+                if ignore_stack.is_ignored(&dir_path.join(n), is_dir) {
+                    return false;
+                }
+            }
+            true
         })
         .collect();
 
-    entries.sort_by(|a, b| ctx.collator.compare(a.name(), b.name()));
+    if ctx.du_sizes.is_some() {
+        entries.sort_by(|a, b| {
+            entry_size(ctx, dir_path, b)
+                .cmp(&entry_size(ctx, dir_path, a))
+                .then_with(|| ctx.collator.compare(a.name(), b.name()))
+        });
+    } else {
+        entries.sort_by(|a, b| ctx.collator.compare(a.name(), b.name()));
+    }
     entries
 }
 
@@ -115,6 +549,9 @@ fn render_dir(
     prefix: &str,
     dir_count: &mut usize,
     file_count: &mut usize,
+    du_scale: Option<u64>,
+    columns: usize,
+    ignore_stack: &IgnoreStack,
 ) {
     let dir_key = dir_path.to_string_lossy();
     let files: Vec<String> = ctx
@@ -126,40 +563,299 @@ fn render_dir(
     let empty = BTreeSet::new();
     let child_dirs = ctx.children.get(dir_path).unwrap_or(&empty);
 
-    let entries = merge_entries(&files, child_dirs, ctx);
+    let entries = merge_entries(&files, child_dirs, ctx, dir_path, ignore_stack);
     let total = entries.len();
     for (i, entry) in entries.iter().enumerate() {
         let is_last = i + 1 == total;
         let connector = if is_last { "└── " } else { "├── " };
         let child_prefix = if is_last { "    " } else { "│\u{a0}\u{a0} " };
 
+        let annotation = if let Some(scale) = du_scale {
+            let size = entry_size(ctx, dir_path, entry);
+            let bar_width = columns.saturating_sub(40).min(30);
+            format!("{} {} ", size_bar(size, scale, bar_width), human_size(size))
+        } else {
+            String::new()
+        };
+
+        let escaped_name = maybe_escape(entry.name(), ctx.no_escape);
+        let kind = entry_kind(ctx, dir_path, entry);
+        let display_name = if let Some(colors) = &ctx.colors {
+            let is_dir = matches!(entry, Entry::Dir(_));
+            let mode = entry_mode(ctx, dir_path, entry);
+            colorize(
+                &escaped_name,
+                color_for(colors, entry.name(), is_dir, mode, kind.as_ref()),
+            )
+        } else {
+            escaped_name
+        };
+        let display_name = match &kind {
+            Some(FileKind::Symlink { target }) => format!("{display_name} -> {target}"),
+            _ => display_name,
+        };
+
         match entry {
-            Entry::File(name) => {
-                println!(
-                    "{}{}{}",
-                    prefix,
-                    connector,
-                    maybe_escape(name, ctx.no_escape)
-                );
+            Entry::File(_) => {
+                println!("{}{}{}{}", prefix, connector, annotation, display_name);
                 *file_count += 1;
             }
             Entry::Dir(name) => {
-                println!(
-                    "{}{}{}",
-                    prefix,
-                    connector,
-                    maybe_escape(name, ctx.no_escape)
-                );
+                println!("{}{}{}{}", prefix, connector, annotation, display_name);
                 *dir_count += 1;
                 let child_path = dir_path.join(name);
+                let child_scale = if du_scale.is_some() {
+                    ctx.du_sizes.as_ref().and_then(|m| m.get(&child_path)).copied()
+                } else {
+                    None
+                };
+                let child_stack = if ctx.use_gitignore {
+                    ignore_stack.push(&child_path)
+                } else {
+                    ignore_stack.clone()
+                };
                 render_dir(
                     ctx,
                     &child_path,
                     &format!("{}{}", prefix, child_prefix),
                     dir_count,
                     file_count,
+                    child_scale,
+                    columns,
+                    &child_stack,
                 );
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{DirEntry, FileEntry};
+
+    fn file(name: &str, size: u64) -> FileEntry {
+        FileEntry {
+            filename: name.into(),
+            size,
+            ctime: 0,
+            mtime: 0,
+            mtime_nsec: 0,
+            mode: 0o100644,
+            mtime_ambiguous: false,
+            kind: FileKind::Regular,
+            xattrs: Vec::new(),
+            content_hash: None,
+        }
+    }
+
+    fn dir_entry(files: Vec<FileEntry>) -> DirEntry {
+        DirEntry {
+            dir_mtime: 0,
+            dir_mtime_nsec: 0,
+            files,
+            mtime_ambiguous: false,
+        }
+    }
+
+    fn children_map(state: &ScanState) -> BTreeMap<PathBuf, BTreeSet<String>> {
+        let mut children: BTreeMap<PathBuf, BTreeSet<String>> = BTreeMap::new();
+        for dir_key in state.dirs.keys() {
+            let dir_path = Path::new(dir_key);
+            if let Some(parent) = dir_path.parent()
+                && let Some(name) = dir_path.file_name()
+            {
+                children
+                    .entry(parent.to_path_buf())
+                    .or_default()
+                    .insert(name.to_string_lossy().into_owned());
+            }
+        }
+        children
+    }
+
+    fn test_collator() -> CollatorBorrowed<'static> {
+        let mut options = CollatorOptions::default();
+        options.strength = Some(Strength::Quaternary);
+        options.alternate_handling = Some(AlternateHandling::Shifted);
+        CollatorBorrowed::try_new(Default::default(), options).unwrap()
+    }
+
+    fn test_ctx<'a>(state: &'a ScanState, patterns: &'a [Pattern], use_gitignore: bool) -> TreeContext<'a> {
+        TreeContext {
+            state,
+            children: children_map(state),
+            patterns,
+            collator: test_collator(),
+            no_escape: true,
+            show_hidden: false,
+            du_sizes: None,
+            use_gitignore,
+            colors: None,
+        }
+    }
+
+    #[test]
+    fn aggregate_size_sums_own_files_and_subdirectories() {
+        let mut state = ScanState::default();
+        state.dirs.insert("/root".into(), dir_entry(vec![file("a.txt", 10)]));
+        state.dirs.insert(
+            "/root/sub".into(),
+            dir_entry(vec![file("b.txt", 20), file("c.txt", 5)]),
+        );
+        let ctx = test_ctx(&state, &[], false);
+
+        let mut memo = HashMap::new();
+        let total = aggregate_size(&ctx, Path::new("/root"), &mut memo);
+
+        assert_eq!(total, 35);
+        assert_eq!(memo[Path::new("/root/sub")], 25);
+    }
+
+    #[test]
+    fn aggregate_size_memoizes_repeated_lookups() {
+        let mut state = ScanState::default();
+        state.dirs.insert("/root".into(), dir_entry(vec![file("a.txt", 1)]));
+        let ctx = test_ctx(&state, &[], false);
+
+        let mut memo = HashMap::new();
+        memo.insert(PathBuf::from("/root"), 999);
+
+        assert_eq!(aggregate_size(&ctx, Path::new("/root"), &mut memo), 999);
+    }
+
+    #[test]
+    fn human_size_formats_with_binary_units() {
+        assert_eq!(human_size(0), "0B");
+        assert_eq!(human_size(512), "512B");
+        assert_eq!(human_size(1536), "1.5K");
+        assert_eq!(human_size(10 * 1024 * 1024), "10.0M");
+    }
+
+    #[test]
+    fn size_bar_fills_proportionally_and_clamps() {
+        assert_eq!(size_bar(0, 100, 10), "[          ]");
+        assert_eq!(size_bar(50, 100, 10), "[#####     ]");
+        assert_eq!(size_bar(100, 100, 10), "[##########]");
+        assert_eq!(size_bar(1000, 100, 10), "[##########]");
+        assert_eq!(size_bar(50, 0, 10), "");
+        assert_eq!(size_bar(50, 100, 0), "");
+    }
+
+    #[test]
+    fn parse_ls_colors_falls_back_to_defaults_and_honors_overrides() {
+        // SAFETY: no other test in this process reads/writes LS_COLORS.
+        unsafe { env::set_var("LS_COLORS", "di=01;36:*.rs=01;33") };
+        let colors = parse_ls_colors();
+        unsafe { env::remove_var("LS_COLORS") };
+
+        assert_eq!(colors.get("di").unwrap(), "01;36");
+        assert_eq!(colors.get("*.rs").unwrap(), "01;33");
+        assert_eq!(colors.get("ex").unwrap(), "01;32"); // default, not overridden
+    }
+
+    #[test]
+    fn color_for_prefers_kind_over_extension() {
+        let mut colors = HashMap::new();
+        colors.insert("di".to_string(), "34".to_string());
+        colors.insert("ln".to_string(), "36".to_string());
+        colors.insert("ex".to_string(), "32".to_string());
+        colors.insert("*.txt".to_string(), "37".to_string());
+
+        assert_eq!(color_for(&colors, "dir", true, 0, None), Some("34"));
+        assert_eq!(
+            color_for(
+                &colors,
+                "link",
+                false,
+                0,
+                Some(&FileKind::Symlink { target: "x".into() }),
+            ),
+            Some("36")
+        );
+        assert_eq!(color_for(&colors, "run", false, 0o755, None), Some("32"));
+        assert_eq!(color_for(&colors, "a.txt", false, 0o644, None), Some("37"));
+        assert_eq!(color_for(&colors, "a.unknown", false, 0o644, None), None);
+    }
+
+    #[test]
+    fn build_json_node_nests_files_and_directories() {
+        let mut state = ScanState::default();
+        state.dirs.insert("/root".into(), dir_entry(vec![file("a.txt", 1)]));
+        state.dirs.insert("/root/sub".into(), dir_entry(vec![file("b.txt", 2)]));
+        let ctx = test_ctx(&state, &[], false);
+
+        let mut dir_count = 1;
+        let mut file_count = 0;
+        let node = build_json_node(
+            &ctx,
+            Path::new("/root"),
+            "root".into(),
+            &mut dir_count,
+            &mut file_count,
+            &IgnoreStack::new(),
+        );
+
+        assert_eq!(node["type"], "directory");
+        assert_eq!(node["contents"].as_array().unwrap().len(), 2); // a.txt + sub/
+        assert_eq!(dir_count, 2);
+        assert_eq!(file_count, 2); // a.txt + b.txt
+    }
+
+    #[test]
+    fn merge_entries_filters_hidden_pattern_and_gitignore_matches() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join(".gitignore"), "*.log\n").unwrap();
+
+        let mut state = ScanState::default();
+        let dir_key = tmp.path().to_string_lossy().into_owned();
+        state.dirs.insert(
+            dir_key,
+            dir_entry(vec![file("keep.txt", 1), file("skip.log", 2), file(".hidden", 3)]),
+        );
+
+        let patterns = vec![Pattern::new("*.tmp").unwrap()];
+        let ctx = test_ctx(&state, &patterns, true);
+        let stack = IgnoreStack::new().push(tmp.path());
+
+        let files: Vec<String> = vec!["keep.txt".into(), "skip.log".into(), ".hidden".into()];
+        let empty = BTreeSet::new();
+        let entries = merge_entries(&files, &empty, &ctx, tmp.path(), &stack);
+
+        let names: Vec<&str> = entries.iter().map(Entry::name).collect();
+        assert_eq!(names, vec!["keep.txt"]);
+    }
+
+    #[test]
+    fn merge_entries_never_descends_into_an_ignored_directory_even_with_a_nested_negation() {
+        // Matches git's own limitation: once a directory is excluded, its
+        // contents (and its own .gitignore) are never consulted, so a `!`
+        // rule underneath an ignored directory can't re-include anything.
+        // See the doc comment on `merge_entries` for the rationale.
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join(".gitignore"), "ignored/\n").unwrap();
+        let sub = tmp.path().join("ignored");
+        std::fs::create_dir(&sub).unwrap();
+        std::fs::write(sub.join(".gitignore"), "!keep.txt\n").unwrap();
+
+        let mut state = ScanState::default();
+        state
+            .dirs
+            .insert(tmp.path().to_string_lossy().into_owned(), dir_entry(vec![]));
+        state
+            .dirs
+            .insert(sub.to_string_lossy().into_owned(), dir_entry(vec![file("keep.txt", 1)]));
+
+        let ctx = test_ctx(&state, &[], true);
+        let stack = IgnoreStack::new().push(tmp.path());
+        let empty = BTreeSet::new();
+        let child_dirs = ctx.children.get(tmp.path()).unwrap_or(&empty);
+
+        let entries = merge_entries(&[], child_dirs, &ctx, tmp.path(), &stack);
+
+        assert!(
+            entries.is_empty(),
+            "the ignored directory itself must be pruned from its parent's listing"
+        );
+    }
+}